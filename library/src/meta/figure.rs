@@ -1,9 +1,12 @@
 use std::str::FromStr;
 
-use super::{Counter, LocalName, Numbering, NumberingPattern, RefAnchor, Supplement};
-use crate::layout::{BlockElem, VElem};
+use super::{
+    Counter, Destination, HeadingElem, LinkElem, LocalName, Numbering, NumberingPattern,
+    RefAnchor, Supplement,
+};
+use crate::layout::{BlockElem, HElem, RepeatElem, TableElem, VElem};
 use crate::prelude::*;
-use crate::text::TextElem;
+use crate::text::{RawElem, TextElem};
 
 /// A anchor to be referenced.
 ///
@@ -52,6 +55,12 @@ impl Show for AnchorElem {
 
 /// A figure with an optional caption.
 ///
+/// A figure whose body is a [`table`]($func/table) is numbered and
+/// labelled as a table rather than a figure, and independently from
+/// figures whose body is anything else. Set `kind` explicitly to override
+/// this, e.g. to give a figure containing raw text its own "Listing"
+/// numbering.
+///
 /// ## Example
 /// ```example
 /// = Pipeline
@@ -64,22 +73,64 @@ impl Show for AnchorElem {
 ///     The molecular testing pipeline.
 ///   ],
 /// ) <lab>
+///
+/// #figure(
+///   raw("fn main() {}", lang: "rust"),
+///   kind: raw,
+///   caption: [A minimal program.],
+/// )
+/// ```
+///
+/// Since figures are [locatable]($func/locate) and outlinable,
+/// [`outline`]($func/outline) can list them separately by `kind`, e.g.
+/// `#outline(target: figure.where(kind: table))` for a list of tables.
+///
+/// A figure nested inside another figure's body is a sub-figure: give the
+/// outer figure a two-part `numbering` pattern and its children are
+/// numbered hierarchically under it, e.g. "2a" and "2b" under parent "2":
+/// ```example
+/// #figure(
+///   numbering: "1a",
+///   grid(
+///     columns: 2,
+///     figure(image("a.png"), caption: [A]),
+///     figure(image("b.png"), caption: [B]),
+///   ),
+///   caption: [Two variants.],
+/// )
 /// ```
 ///
 /// Display: Figure
 /// Category: meta
-#[element(Locatable, Synthesize, Show, LocalName, RefAnchor)]
+#[element(Locatable, Synthesize, Show, LocalName, RefAnchor, Outlinable)]
 pub struct FigureElem {
     /// The content of the figure. Often, an [image]($func/image).
     #[required]
     pub body: Content,
 
+    /// The kind of figure this is, used to select an independent counter,
+    /// [local name]($func/figure.local-name) and default supplement from
+    /// other kinds of figures.
+    ///
+    /// Automatically determined if left at its default: from the first
+    /// figure nested in `body` (for a figure of sub-figures), or else from
+    /// the element at the root of `body`. Set this explicitly (e.g. to an element
+    /// function like `table` or `raw`) when the body isn't itself of the
+    /// kind that should be used for numbering, such as a styled wrapper
+    /// around a table.
+    pub kind: Smart<ElemFunc>,
+
     /// Supplement prefix text in the caption.
     pub supplement: Smart<Option<Supplement>>,
 
     /// Counter used in this figure for numbering.
-    #[default(Counter::of(Self::func()))]
-    pub counter: Counter,
+    ///
+    /// Defaults to an independent counter keyed on this figure's resolved
+    /// `kind`, shared with every other figure of that kind (including
+    /// nested sub-figures). Set this explicitly to make the figure use
+    /// some other counter instead; an explicit counter is shared down
+    /// into sub-figures just like the default one.
+    pub counter: Smart<Counter>,
 
     /// How to number the figure. Accepts a
     /// [numbering pattern or function]($func/numbering).
@@ -96,31 +147,130 @@ pub struct FigureElem {
     /// The vertical gap between the body and caption.
     #[default(Em::new(0.65).into())]
     pub gap: Length,
+
+    /// The nesting level of this figure, used to scope its counter
+    /// stepping, analogous to [`AnchorElem`]'s own `level`.
+    ///
+    /// Automatically set to one more than the enclosing figure's level for
+    /// any figure nested in another figure's `body`, so that e.g. two
+    /// images nested inside a `table`-kind outer figure numbered with
+    /// `numbering: "1a"` come out as "2a" and "2b" under parent figure "2".
+    /// Figures not nested in another figure stay at the default, `1`.
+    #[default(NonZeroUsize::ONE)]
+    pub level: NonZeroUsize,
 }
 
 impl Synthesize for FigureElem {
     fn synthesize(&mut self, styles: StyleChain) {
+        let kind = self.resolved_kind(styles);
+        self.push_kind(Smart::Custom(kind));
+
+        let counter = match self.counter(styles) {
+            Smart::Custom(counter) => counter,
+            Smart::Auto => Counter::of(kind),
+        };
+        self.push_counter(Smart::Custom(counter));
+
         self.push_numbering(self.numbering(styles));
     }
 }
 
-impl Show for FigureElem {
-    fn show(&self, vt: &mut Vt, styles: StyleChain) -> SourceResult<Content> {
-        let mut realized = self.body();
+impl FigureElem {
+    /// Resolves `kind`, falling back to the kind of the first figure nested
+    /// in `body` (e.g. for a `grid` of sub-figures), and then to the
+    /// function of the element at the root of `body`. Kept separate from
+    /// the `Synthesize` impl so it can also be used before synthesis has
+    /// run, e.g. from a bare query.
+    fn resolved_kind(&self, styles: StyleChain) -> ElemFunc {
+        match self.kind(styles) {
+            Smart::Custom(func) => func,
+            Smart::Auto => self
+                .first_sub_figure()
+                .map(|fig| fig.resolved_kind(styles))
+                .unwrap_or_else(|| self.body().func()),
+        }
+    }
+
+    /// The first figure directly nested in `body`, if any. A figure of
+    /// sub-figures (e.g. a `grid` of images) uses this to adopt their kind
+    /// instead of its own container's, so it shares a single counter
+    /// series with plain sibling figures of that kind.
+    fn first_sub_figure(&self) -> Option<Self> {
+        self.body()
+            .query(Selector::Elem(Self::func(), None))
+            .into_iter()
+            .next()
+            .and_then(|elem| elem.to::<Self>().cloned())
+    }
+
+    /// Resolves `counter`, falling back to the per-`kind` counter used by
+    /// `synthesize`. Kept separate for the same reason as `resolved_kind`.
+    fn resolved_counter(&self, styles: StyleChain) -> Counter {
+        match self.counter(styles) {
+            Smart::Custom(counter) => counter,
+            Smart::Auto => Counter::of(self.resolved_kind(styles)),
+        }
+    }
 
+    /// Builds the numbering and caption, e.g. "Table 1: Some data.". This is
+    /// shown below the figure's body and, reused verbatim, as its entry in a
+    /// figure outline.
+    fn caption_line(&self, vt: &mut Vt, styles: StyleChain) -> SourceResult<Content> {
         let mut cap = Content::empty();
 
         if self.numbering(styles).is_some() {
             cap += self.anchor(vt, styles)?.show(vt, styles)?;
         }
 
-        if let Some(caption) = self.caption(styles) {
+        let caption = self.caption(styles).or_else(|| self.aggregated_sub_captions(styles));
+        if let Some(caption) = caption {
             if !cap.is_empty() {
                 cap += self.sep(styles).unwrap_or_default();
             }
             cap += caption
         }
 
+        Ok(cap)
+    }
+
+    /// When no caption is set explicitly, join the captions of any directly
+    /// nested sub-figures (e.g. figures placed in a `grid` inside `body`)
+    /// into one caption for this figure, so a figure of sub-figures still
+    /// gets a meaningful caption without repeating it by hand.
+    fn aggregated_sub_captions(&self, styles: StyleChain) -> Option<Content> {
+        let mut joined = Content::empty();
+        let mut any = false;
+
+        for elem in self.body().query(Selector::Elem(Self::func(), None)) {
+            let Some(child) = elem.to::<Self>() else { continue };
+            let Some(caption) = child.caption(styles) else { continue };
+
+            if any {
+                joined += TextElem::packed(", ");
+            }
+            joined += caption;
+            any = true;
+        }
+
+        any.then_some(joined)
+    }
+}
+
+impl Show for FigureElem {
+    fn show(&self, vt: &mut Vt, styles: StyleChain) -> SourceResult<Content> {
+        // Propagate this figure's identity to any nested sub-figure so it
+        // shares the same counter (and therefore steps hierarchically under
+        // it) and the same numbering pattern, unless it overrides either
+        // itself.
+        let child_level = NonZeroUsize::new(self.level(styles).get() + 1).unwrap();
+        let mut realized = self
+            .body()
+            .styled(FigureElem::set_level(child_level))
+            .styled(FigureElem::set_kind(self.kind(styles)))
+            .styled(FigureElem::set_counter(self.counter(styles)))
+            .styled(FigureElem::set_numbering(self.numbering(styles)));
+        let cap = self.caption_line(vt, styles)?;
+
         if !cap.is_empty() {
             realized += VElem::weak(self.gap(styles).into()).pack();
             realized += cap;
@@ -136,6 +286,31 @@ impl Show for FigureElem {
 
 impl LocalName for FigureElem {
     fn local_name(&self, lang: Lang) -> &'static str {
+        kind_local_name(self.resolved_kind(StyleChain::default()), lang)
+    }
+}
+
+impl RefAnchor for FigureElem {
+    fn anchor(&self, vt: &mut Vt, styles: StyleChain) -> SourceResult<AnchorElem> {
+        let supplement = Supplement::resolve(self.supplement(styles), vt, self, styles)?;
+        Ok(AnchorElem::new(self.resolved_counter(styles), supplement, self.numbering(styles))
+            .with_level(self.level(styles)))
+    }
+}
+
+/// The local name for a figure of the given `kind`, in `lang`.
+///
+/// A free function (rather than a method) so it can be reused by anything
+/// that only has a `kind` at hand, not a full `FigureElem`.
+fn kind_local_name(kind: ElemFunc, lang: Lang) -> &'static str {
+    if kind == TableElem::func() {
+        match lang {
+            Lang::GERMAN => "Tabelle",
+            Lang::ENGLISH | _ => "Table",
+        }
+    } else if kind == RawElem::func() {
+        "Listing"
+    } else {
         match lang {
             Lang::GERMAN => "Abbildung",
             Lang::ENGLISH | _ => "Figure",
@@ -143,9 +318,156 @@ impl LocalName for FigureElem {
     }
 }
 
-impl RefAnchor for FigureElem {
-    fn anchor(&self, vt: &mut Vt, styles: StyleChain) -> SourceResult<AnchorElem> {
-        let supplement = Supplement::resolve(self.supplement(styles), vt, self, styles)?;
-        Ok(AnchorElem::new(self.counter(styles), supplement, self.numbering(styles)))
+/// An element that can be listed in an [`OutlineElem`], e.g. a figure.
+///
+/// Defined alongside `FigureElem` and `OutlineElem` because `FigureElem` is,
+/// for now, this crate's only implementor.
+pub trait Outlinable: Locatable {
+    /// How deeply this entry nests, e.g. a heading's level or a
+    /// sub-figure's level relative to its parent. Entries past level one
+    /// are not listed at the top level of an outline; `OutlineElem` lists
+    /// figures nested in a top-level figure's body right after that
+    /// figure's entry, all at the same single indent, instead.
+    fn level(&self, styles: StyleChain) -> NonZeroUsize {
+        NonZeroUsize::ONE
+    }
+
+    /// Builds the content shown for this element's entry, e.g. its number
+    /// and caption. `None` omits the element from the outline entirely.
+    fn outline(&self, vt: &mut Vt, styles: StyleChain) -> SourceResult<Option<Content>>;
+}
+
+impl Outlinable for FigureElem {
+    fn level(&self, styles: StyleChain) -> NonZeroUsize {
+        self.level(styles)
+    }
+
+    fn outline(&self, vt: &mut Vt, styles: StyleChain) -> SourceResult<Option<Content>> {
+        let cap = self.caption_line(vt, styles)?;
+        if cap.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(cap))
+    }
+}
+
+/// A list of [outlinable]($func/outline) elements, analogous to a
+/// page-level table of contents. Figures are outlinable, so
+/// `#outline(target: figure.where(kind: table))` produces a "List of
+/// Tables"; `#outline(target: figure)` a plain "List of Figures".
+///
+/// Walks every [locatable]($func/locate) element matching `target` in
+/// layout order and lists the content built by its own
+/// [`Outlinable::outline`] (in turn the same numbering and caption shown
+/// below the figure itself), followed by dot leaders and a page number
+/// linking to it. Sub-figures (those with an [`Outlinable::level`] above
+/// one) are nested under their parent's own entry rather than flattened
+/// alongside top-level ones.
+///
+/// ## Example
+/// ```example
+/// #outline(target: figure.where(kind: table))
+///
+/// #figure(
+///   table(columns: 2)[A][B],
+///   caption: [Some data.],
+/// )
+/// ```
+///
+/// Display: Outline
+/// Category: meta
+#[element(Show)]
+pub struct OutlineElem {
+    /// The elements to list, restricted to [`Outlinable`] ones. Defaults
+    /// to every figure; combine with `.where(..)` to filter further, e.g.
+    /// by `kind`.
+    #[default(Selector::Elem(FigureElem::func(), None))]
+    pub target: Selector,
+
+    /// The title of the list.
+    ///
+    /// - `auto`: named after `target`'s figure `kind`, if any, e.g. "List
+    ///   of Tables", or "List of Figures" for an unrestricted `target`.
+    /// - `none`: no title.
+    /// - Custom content replaces the title entirely.
+    #[default(Smart::Auto)]
+    pub title: Smart<Option<Content>>,
+}
+
+impl OutlineElem {
+    /// The "List of <Kind>s" title used when `title` is left at `auto`.
+    /// Only `figure`-based targets are named after a kind; anything else
+    /// falls back to a plain "Figure".
+    fn default_title(&self, styles: StyleChain) -> Content {
+        let kind = match self.target(styles) {
+            Selector::Elem(func, fields) if func == FigureElem::func() => fields
+                .and_then(|fields| fields.iter().find(|(name, _)| name.as_str() == "kind").cloned())
+                .and_then(|(_, value)| value.cast::<ElemFunc>().ok()),
+            _ => None,
+        };
+        let name = match kind {
+            Some(kind) => kind_local_name(kind, TextElem::lang_in(styles)),
+            None => "Figure",
+        };
+        TextElem::packed(eco_format!("List of {name}s"))
+    }
+
+    /// Builds one linked, dot-leadered entry for an outlinable element,
+    /// indented by `indent` to reflect its nesting under a parent entry.
+    fn entry(
+        &self,
+        vt: &mut Vt,
+        styles: StyleChain,
+        elem: &Content,
+        indent: Length,
+    ) -> SourceResult<Option<Content>> {
+        let Some(outlinable) = elem.with::<dyn Outlinable>() else { return Ok(None) };
+        let Some(location) = elem.location() else { return Ok(None) };
+        let Some(body) = outlinable.outline(vt, styles)? else { return Ok(None) };
+
+        let mut entry = HElem::new(indent.into()).pack();
+        entry += body;
+        // Dot leaders fill the remaining line width up to the page number.
+        entry += RepeatElem::new(TextElem::packed(".")).pack();
+        entry += HElem::new(Abs::pt(2.0).into()).pack();
+        entry += TextElem::packed(eco_format!("{}", vt.introspector().page(location)));
+
+        Ok(Some(LinkElem::new(Destination::Location(location), entry).pack()))
+    }
+}
+
+impl Show for OutlineElem {
+    fn show(&self, vt: &mut Vt, styles: StyleChain) -> SourceResult<Content> {
+        let mut seq = vec![];
+
+        let title = match self.title(styles) {
+            Smart::Auto => Some(self.default_title(styles)),
+            Smart::Custom(title) => title,
+        };
+        if let Some(title) = title {
+            seq.push(HeadingElem::new(title).with_level(NonZeroUsize::ONE).pack());
+        }
+
+        for elem in vt.introspector().query(&self.target(styles)) {
+            let Some(outlinable) = elem.with::<dyn Outlinable>() else { continue };
+            if outlinable.level(styles) > NonZeroUsize::ONE {
+                // Listed below, nested under its parent, instead.
+                continue;
+            }
+
+            let Some(entry) = self.entry(vt, styles, &elem, Length::zero())? else { continue };
+            seq.push(entry);
+
+            let Some(figure) = elem.to::<FigureElem>() else { continue };
+            for child in figure.body().query(Selector::Elem(FigureElem::func(), None)) {
+                let Some(entry) = self.entry(vt, styles, &child, Abs::pt(12.0).into())? else {
+                    continue;
+                };
+                seq.push(entry);
+            }
+        }
+
+        Ok(Content::sequence(seq))
     }
 }